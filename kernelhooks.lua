@@ -46,6 +46,7 @@ function kernelhooks.filter(path)
 end
 
 function kernelhooks.add()
+	rpm.execute("/usr/bin/sdbootutil", "batch-begin")
 	for kver in pairs(kernelhooks.found) do
 		if kernelhooks.legacy[kver] then
 			_log("not adding " .. kver .. " due to legacy /boot location")
@@ -56,9 +57,11 @@ function kernelhooks.add()
 			rpm.execute("/usr/bin/sdbootutil", "set-default-snapshot")
 		end
 	end
+	rpm.execute("/usr/bin/sdbootutil", "batch-commit")
 end
 
 function kernelhooks.remove()
+	rpm.execute("/usr/bin/sdbootutil", "batch-begin")
 	for kver in pairs(kernelhooks.found) do
 		if kernelhooks.legacy[kver] then
 			_log("not removing " .. kver .. " due to legacy /boot location")
@@ -68,5 +71,6 @@ function kernelhooks.remove()
 			rpm.execute("/usr/bin/sdbootutil", "set-default-snapshot")
 		end
 	end
+	rpm.execute("/usr/bin/sdbootutil", "batch-commit")
 end
 
@@ -42,6 +42,8 @@ Requires:       dialog
 # While systemd-pcrlock is in experimental
 Requires:       systemd-experimental
 Requires:       dracut-pcr-signature
+# for sdbootutil watch
+Suggests:       inotify-tools
 Supplements:    (systemd-boot and shim)
 Requires:       (%{name}-snapper if (snapper and btrfsprogs))
 ExclusiveArch:  aarch64 ppc64le riscv64 x86_64
@@ -91,7 +93,7 @@ package may disable other plugin scripts that are incompatible.
 install -D -m 755 sdbootutil %{buildroot}%{_bindir}/sdbootutil
 
 # services
-for i in sdbootutil-update-predictions.service; do
+for i in sdbootutil-update-predictions.service sdbootutil-watch.service; do
 	install -D -m 644 "$i" %{buildroot}%{_unitdir}/"$i"
 done
 
@@ -142,6 +144,7 @@ sdbootutil update
 %license LICENSE
 %{_bindir}/sdbootutil
 %{_unitdir}/sdbootutil-update-predictions.service
+%{_unitdir}/sdbootutil-watch.service
 
 %files rpm-scriptlets
 %dir %{_prefix}/lib/module-init-tools
@@ -0,0 +1,218 @@
+#!/bin/bash
+# Tests of the functions of sdbootutil that work without an ESP.  The
+# functions are taken from the script one by one.  bootctl is a stub
+# set with SDB_BOOTCTL, nothing touches the ESP of the machine running
+# the tests.
+#
+# Usage: tests/run-tests.sh
+
+set -e
+shopt -s nullglob
+
+script="$(cd "${0%/*}/.." && pwd)/sdbootutil"
+tmpdir="$(mktemp -d -t sdbootutil-tests.XXXXXX)"
+trap 'rm -rf "$tmpdir"' EXIT
+
+nl=$'\n'
+passed=0
+failed=0
+
+# Define functions of sdbootutil by name
+load()
+{
+	local f
+	for f in "$@"; do
+		eval "$(sed -n "/^$f()/,/^}/p" "$script")"
+		declare -F "$f" > /dev/null || { echo "No function $f in $script" >&2; exit 1; }
+	done
+}
+
+check()
+{
+	local name="$1"
+	local expected="$2"
+	local actual="$3"
+	if [ "$expected" = "$actual" ]; then
+		((++passed))
+		return 0
+	fi
+	((++failed))
+	echo "FAIL: $name"
+	echo "  expected: $expected"
+	echo "  actual:   $actual"
+}
+
+err()
+{
+	echo "$*" >&2
+	exit 1
+}
+
+log_info()
+{
+	:
+}
+
+warn()
+{
+	echo "$*" >&2
+}
+
+# fat_safe_name
+load fat_safe_name
+long="$(printf 'a%.0s' {1..200})"
+check "fat_safe_name cuts long tokens to 128 characters" "128" "$(name="$(fat_safe_name "$long")"; echo "${#name}")"
+check "fat_safe_name of a long token is stable" "$(fat_safe_name "${long}x")" "$(fat_safe_name "${long}y")"
+check "fat_safe_name strips trailing dots and spaces after cutting" \
+	"$(printf 'b%.0s' {1..126})" "$(fat_safe_name "$(printf 'b%.0s' {1..126}). .tail")"
+check "fat_safe_name replaces characters FAT does not allow" \
+	"a_b_c_d_e_f_g_h_i" "$(fat_safe_name 'a"b*c/d:e<f>g?h\i')"
+check "fat_safe_name keeps the case" "openSUSE-Tumbleweed" "$(fat_safe_name "openSUSE-Tumbleweed")"
+
+# check_token_collision
+load check_token_collision
+boot_root="$tmpdir/esp"
+mkdir -p "$boot_root/loader" "$boot_root/opensuse-tumbleweed" "$boot_root/EFI"
+check "check_token_collision accepts the own directory" "0" \
+	"$(entry_token=opensuse-tumbleweed; (check_token_collision) 2>/dev/null; echo "$?")"
+check "check_token_collision accepts a new token" "0" \
+	"$(entry_token=opensuse-leap; (check_token_collision) 2>/dev/null; echo "$?")"
+check "check_token_collision rejects a token differing in case" "1" \
+	"$(entry_token=openSUSE-Tumbleweed; (check_token_collision) 2>/dev/null; echo "$?")"
+check "check_token_collision names the colliding directory" \
+	"Entry token Efi collides with $boot_root/EFI on the case-insensitive ESP. Use --entry-token to choose a different one" \
+	"$(entry_token=Efi; (check_token_collision) 2>&1)"
+mkdir "$boot_root/$(fat_safe_name "${long}x")"
+check "check_token_collision rejects a long token differing in case" "1" \
+	"$(entry_token="$(fat_safe_name "${long^^}")"; (check_token_collision) 2>/dev/null; echo "$?")"
+rm -rf "$boot_root"
+
+# bootctl
+cat > "$tmpdir/bootctl" <<'EOF'
+#!/bin/sh
+[ "$1" = list ] && { echo "[]"; exit 0; }
+echo "unexpected bootctl $*" >&2
+exit 1
+EOF
+chmod +x "$tmpdir/bootctl"
+export SDB_BOOTCTL="$tmpdir/bootctl"
+
+eval "$(grep '^override_tools=' "$script")"
+declare -A tool_reported=()
+verbose=
+load run_tool setup_tool_overrides
+setup_tool_overrides
+check "SDB_BOOTCTL is used for bootctl" "[]" "$(bootctl list --json=short)"
+check "--help with SDB_BOOTCTL" "0" "$("$script" --help 2>&1 | grep -c 'unexpected bootctl')"
+
+# sedrootflags
+load gpt_auto_root sedrootflags
+root_uuid=1234
+root_device=/dev/vda2
+have_snapshots=1
+usr_snapshots=
+subvol_option=rootflags
+machine_id=0123456789abcdef0123456789abcdef
+SDB_GPT_AUTO_ROOT=
+gpt_auto_checked=
+subvol=/@/.snapshots/5/snapshot
+check "sedrootflags adds root, rootflags and machine-id" \
+	"root=UUID=1234 quiet rootflags=subvol=$subvol systemd.machine_id=$machine_id" \
+	"$(echo "BOOT_IMAGE=/boot/vmlinuz root=/dev/sda2	 quiet" | sedrootflags "$subvol")"
+check "sedrootflags replaces root, rootflags and machine-id" \
+	"root=UUID=1234 rootflags=subvol=$subvol splash=silent systemd.machine_id=$machine_id" \
+	"$(echo "root=UUID=old rootflags=subvol=/@/.snapshots/1/snapshot initrd=/x splash=silent systemd.machine_id=ffff" | sedrootflags "$subvol")"
+check "sedrootflags without snapshots" \
+	"quiet root=UUID=1234 systemd.machine_id=$machine_id" \
+	"$(have_snapshots=; echo "quiet" | sedrootflags "")"
+check "sedrootflags drops root= in gpt-auto mode" \
+	"quiet rootflags=subvol=$subvol systemd.machine_id=$machine_id" \
+	"$(SDB_GPT_AUTO_ROOT=1; gpt_auto_checked=0; echo "root=UUID=old quiet" | sedrootflags "$subvol")"
+
+# cmdline_user_options
+load cmdline_user_options
+check "cmdline_user_options keeps only options of the user" \
+	"mitigations=auto${nl}quiet${nl}splash=silent" \
+	"$(echo "BOOT_IMAGE=/x root=UUID=1 rootflags=subvol=a  quiet	splash=silent resume=/dev/vda3 systemd.machine_id=1 mitigations=auto quiet" | cmdline_user_options)"
+check "cmdline_user_options of an empty cmdline" "" "$(echo "  " | cmdline_user_options)"
+
+# print_table
+load table_row print_table
+table_rows=()
+arg_no_headers=
+arg_porcelain=
+table_row a bb
+table_row ccc d
+check "print_table aligns the columns" \
+	"NAME  X${nl}a     bb${nl}ccc   d" \
+	"$(print_table NAME X)"
+table_rows=()
+table_row "\033[1mab\033[0m" x
+table_row abc y
+check "print_table ignores colors for the width" \
+	"$(printf '\033[1mab\033[0m   x\nabc  y')" \
+	"$(arg_no_headers=1; print_table A B)"
+table_rows=()
+table_row "\033[1mab\033[0m" x
+check "print_table --porcelain" \
+	"$(printf 'ab\tx')" \
+	"$(arg_porcelain=1; print_table A B)"
+
+# recompress_initrd format detection
+load recompress_initrd
+SDB_INITRD_KEEP=
+seq 1 20000 > "$tmpdir/content"
+for format in gzip zstd xz lz4; do
+	command -v "$format" > /dev/null || { echo "SKIP: recompress_initrd of $format, not installed"; continue; }
+	for SDB_INITRD_COMPRESS in zstd xz; do
+		command -v "$SDB_INITRD_COMPRESS" > /dev/null || continue
+		"$format" -c < "$tmpdir/content" > "$tmpdir/initrd"
+		recompress_initrd "$tmpdir/initrd" 1.0-test
+		check "recompress_initrd of $format with $SDB_INITRD_COMPRESS keeps the content" \
+			"$(cat "$tmpdir/content")" "$("${SDB_INITRD_COMPRESS}" -dc < "$tmpdir/initrd" 2>/dev/null || "$format" -dc < "$tmpdir/initrd")"
+	done
+done
+if command -v gzip > /dev/null && command -v zstd > /dev/null; then
+	gzip -1 -c < "$tmpdir/content" > "$tmpdir/initrd"
+	SDB_INITRD_COMPRESS=zstd:19
+	recompress_initrd "$tmpdir/initrd" 1.0-test
+	check "recompress_initrd of gzip to zstd" "28b52ffd" "$(od -An -tx1 -N4 "$tmpdir/initrd" | tr -d ' ')"
+fi
+echo "not an initrd" > "$tmpdir/initrd"
+SDB_INITRD_COMPRESS=zstd
+recompress_initrd "$tmpdir/initrd" 1.0-test
+check "recompress_initrd leaves unknown formats alone" "not an initrd" "$(cat "$tmpdir/initrd")"
+check "recompress_initrd rejects unknown algorithms" "1" \
+	"$(SDB_INITRD_COMPRESS=bzip2; (recompress_initrd "$tmpdir/initrd" 1.0-test) 2>/dev/null; echo "$?")"
+check "recompress_initrd rejects invalid levels" "1" \
+	"$(SDB_INITRD_COMPRESS=zstd:high; (recompress_initrd "$tmpdir/initrd" 1.0-test) 2>/dev/null; echo "$?")"
+
+# exit status of ensure-*
+check "invalid usage exits with 3" "3" "$("$script" --bogus 2>/dev/null; echo "$?")"
+check "invalid usage of ensure-installed exits with 2" "2" "$("$script" ensure-installed --bogus 2>/dev/null; echo "$?")"
+mkdir -p "$tmpdir/esp"
+check "errors of ensure-entries exit with 2" "2" \
+	"$("$script" ensure-entries --esp-path "$tmpdir/esp" 2>/dev/null; echo "$?")"
+(
+	load cleanup
+	rollback_files() { :; }
+	esp_sync_end() { :; }
+	print_timings() { :; }
+	change_summary() { :; }
+	journal_write() { :; }
+	exit_error=2
+	exit_changed=1
+	check "ensure-* exits with 2 when set -e stops it" "2" \
+		"$(ensure_mode=1; (tmpdir="$tmpdir/x"; trap cleanup EXIT; false); echo "$?")"
+	check "ensure-* exits with 1 when something changed" "1" \
+		"$(ensure_mode=1; ensure_changed=1; (tmpdir="$tmpdir/x"; trap cleanup EXIT; exit "$exit_changed"); echo "$?")"
+	check "ensure-* exits with 0 when nothing changed" "0" \
+		"$(ensure_mode=1; (tmpdir="$tmpdir/x"; trap cleanup EXIT; true); echo "$?")"
+	check "other commands keep the status" "1" \
+		"$(ensure_mode=; (tmpdir="$tmpdir/x"; trap cleanup EXIT; false); echo "$?")"
+	echo "$passed $failed" > "$tmpdir/counts"
+)
+read -r passed failed < "$tmpdir/counts"
+
+echo "$passed passed, $failed failed"
+[ "$failed" = 0 ]